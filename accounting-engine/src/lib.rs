@@ -2,16 +2,44 @@ use wasm_bindgen::prelude::*;
 use rust_decimal::Decimal;
 use rust_decimal::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 // ==================== Data Structures ====================
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BookingInput {
     pub cost_amount: f64,
     pub sale_amount: f64,
     pub vat_rate: f64,
     pub commission_rate: f64,
     pub currency: String,
+    /// Currency the cost was incurred in. Defaults to `currency` (the sale currency)
+    /// when omitted, i.e. a single-currency booking.
+    #[serde(default)]
+    pub cost_currency: Option<String>,
+    /// Rate-to-base-currency table, e.g. `{"EUR": 1.08, "GBP": 1.27}`. Only consulted
+    /// for currency codes other than `base_currency`.
+    #[serde(default)]
+    pub price_oracle: Option<HashMap<String, f64>>,
+    /// Currency all financials are reported in. Defaults to `currency` when omitted.
+    #[serde(default)]
+    pub base_currency: Option<String>,
+    /// When true, no VAT is charged: `vat_amount` is zero and the full sale counts as
+    /// `net_before_vat`.
+    #[serde(default)]
+    pub vat_exempt: bool,
+    /// Cost centre this booking is attributed to, used to group the VAT summary report.
+    #[serde(default)]
+    pub cost_centre: Option<String>,
+    /// Previous invoice number (e.g. `"INV-2024-0042"`). When present, its successor is
+    /// stamped into every journal entry's description so the balanced set shares one
+    /// invoice reference.
+    #[serde(default)]
+    pub last_invoice_number: Option<String>,
+    /// ISO date the booking was made on. Used to exclude it from a period-scoped trial
+    /// balance run when it falls outside `BatchBookingInput`'s `start_date`/`end_date`.
+    #[serde(default)]
+    pub booking_date: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -23,6 +51,107 @@ pub struct BookingFinancials {
     pub commission_amount: f64,
     pub net_profit: f64,
     pub profit_margin_percentage: f64,
+    /// Base-currency profit effect of cost_rate differing from sale_rate, scaled by the
+    /// cost amount. Both rates come from one `price_oracle` snapshot (there is no
+    /// booking-time vs. settlement-time rate), so this is a conversion-rate effect, not
+    /// a realized gain tracked over a holding period.
+    pub fx_gain_loss: f64,
+}
+
+/// Looks up the rate that converts an amount in `code` into the booking's base currency.
+/// The base currency itself always converts at 1.0 and never needs an oracle entry.
+fn lookup_base_rate(code: &str, base_currency: &str, oracle: &HashMap<String, f64>) -> Result<Decimal, CalcError> {
+    if code == base_currency {
+        return Ok(Decimal::ONE);
+    }
+    match oracle.get(code) {
+        Some(rate) => {
+            if *rate <= 0.0 {
+                return Err(CalcError::NegativeRate);
+            }
+            Decimal::from_f64(*rate).ok_or(CalcError::InvalidAmount)
+        }
+        None => Err(CalcError::CurrencyMismatch(code.to_string())),
+    }
+}
+
+// ==================== Error Handling ====================
+
+/// Errors from the checked financial math. Replaces the old behavior of collapsing
+/// NaN/overflow/divide-by-zero into `Decimal::ZERO`, which produced "balanced" but wrong
+/// journals.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalcError {
+    InvalidAmount,
+    Overflow,
+    NegativeRate,
+    CurrencyMismatch(String),
+    InvalidInvoiceNumber,
+}
+
+impl CalcError {
+    fn error_code(&self) -> &'static str {
+        match self {
+            CalcError::InvalidAmount => "invalid_amount",
+            CalcError::Overflow => "overflow",
+            CalcError::NegativeRate => "negative_rate",
+            CalcError::CurrencyMismatch(_) => "currency_mismatch",
+            CalcError::InvalidInvoiceNumber => "invalid_invoice_number",
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            CalcError::InvalidAmount => {
+                "cost_amount, sale_amount and vat_rate must be valid numbers, non-negative, and vat_rate must be below 100".to_string()
+            }
+            CalcError::Overflow => "a calculation step overflowed or divided by zero".to_string(),
+            CalcError::NegativeRate => {
+                "vat_rate and commission_rate must be non-negative, and price_oracle rates must be positive".to_string()
+            }
+            CalcError::CurrencyMismatch(code) => format!("missing price-oracle rate for currency '{}'", code),
+            CalcError::InvalidInvoiceNumber => {
+                "the invoice number must contain a numeric segment to increment".to_string()
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CalcErrorResponse {
+    error_code: String,
+    message: String,
+}
+
+fn calc_error_response(err: CalcError) -> String {
+    let response = CalcErrorResponse {
+        error_code: err.error_code().to_string(),
+        message: err.message(),
+    };
+    serde_json::to_string(&response).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Rejects bookings with invalid amounts/rates up front so a single malformed booking can't
+/// silently corrupt a batch.
+fn validate_booking_input(input: &BookingInput) -> Result<(), CalcError> {
+    if input.cost_amount < 0.0 || input.sale_amount < 0.0 {
+        return Err(CalcError::InvalidAmount);
+    }
+    if input.vat_rate < 0.0 || input.commission_rate < 0.0 {
+        return Err(CalcError::NegativeRate);
+    }
+    if input.vat_rate >= 100.0 {
+        return Err(CalcError::InvalidAmount);
+    }
+    Ok(())
+}
+
+fn checked_div(a: Decimal, b: Decimal) -> Result<Decimal, CalcError> {
+    a.checked_div(b).ok_or(CalcError::Overflow)
+}
+
+fn to_f64_checked(value: Decimal) -> Result<f64, CalcError> {
+    value.to_f64().ok_or(CalcError::Overflow)
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -44,71 +173,179 @@ pub struct JournalEntries {
 
 // ==================== Booking Calculations ====================
 
-#[wasm_bindgen]
-pub fn calculate_booking_financials(input_json: &str) -> String {
-    let input: BookingInput = match serde_json::from_str(input_json) {
-        Ok(val) => val,
-        Err(e) => return format!("{{\"error\": \"Invalid input: {}\"}}", e),
-    };
+fn calculate_booking_financials_checked(input: &BookingInput) -> Result<BookingFinancials, CalcError> {
+    validate_booking_input(input)?;
 
     // Convert to Decimal for precision
-    let cost = Decimal::from_f64(input.cost_amount).unwrap_or(Decimal::ZERO);
-    let sale = Decimal::from_f64(input.sale_amount).unwrap_or(Decimal::ZERO);
-    let vat_rate = Decimal::from_f64(input.vat_rate).unwrap_or(Decimal::ZERO);
-    let commission_rate = Decimal::from_f64(input.commission_rate).unwrap_or(Decimal::ZERO);
+    let cost = Decimal::from_f64(input.cost_amount).ok_or(CalcError::InvalidAmount)?;
+    let sale = Decimal::from_f64(input.sale_amount).ok_or(CalcError::InvalidAmount)?;
+    let vat_rate = Decimal::from_f64(input.vat_rate).ok_or(CalcError::InvalidAmount)?;
+    let commission_rate = Decimal::from_f64(input.commission_rate).ok_or(CalcError::InvalidAmount)?;
+
+    let base_currency = input.base_currency.clone().unwrap_or_else(|| input.currency.clone());
+    let cost_currency = input.cost_currency.clone().unwrap_or_else(|| input.currency.clone());
+    let oracle = input.price_oracle.clone().unwrap_or_default();
+
+    let sale_rate = lookup_base_rate(&input.currency, &base_currency, &oracle)?;
+    let cost_rate = lookup_base_rate(&cost_currency, &base_currency, &oracle)?;
+
+    // Convert cost and sale into the base currency before any profit/VAT/commission math.
+    let sale_in_base = sale.checked_mul(sale_rate).ok_or(CalcError::Overflow)?;
+    let cost_in_base = cost.checked_mul(cost_rate).ok_or(CalcError::Overflow)?;
+
+    // Isolates the portion of base-currency profit attributable purely to cost_rate
+    // differing from sale_rate, scaled by the actual cost amount (not the whole sale).
+    // Both rates come from the same single-snapshot `price_oracle`, so this is a
+    // same-instant conversion-rate effect, not a realized gain tracked over time.
+    let rate_diff = sale_rate.checked_sub(cost_rate).ok_or(CalcError::Overflow)?;
+    let fx_gain_loss = cost.checked_mul(rate_diff).ok_or(CalcError::Overflow)?;
+
+    let cost = cost_in_base;
+    let sale = sale_in_base;
 
     // Calculate gross profit
-    let gross_profit = sale - cost;
+    let gross_profit = sale.checked_sub(cost).ok_or(CalcError::Overflow)?;
 
-    // Calculate VAT (assuming VAT is included in sale price)
-    let vat_divisor = Decimal::ONE + (vat_rate / Decimal::from(100));
-    let net_before_vat = sale / vat_divisor;
-    let vat_amount = sale - net_before_vat;
+    // Calculate VAT (assuming VAT is included in sale price), unless the booking is VAT-exempt
+    let (net_before_vat, vat_amount) = if input.vat_exempt {
+        (sale, Decimal::ZERO)
+    } else {
+        let vat_divisor = Decimal::ONE
+            .checked_add(checked_div(vat_rate, Decimal::from(100))?)
+            .ok_or(CalcError::Overflow)?;
+        let net_before_vat = checked_div(sale, vat_divisor)?;
+        let vat_amount = sale.checked_sub(net_before_vat).ok_or(CalcError::Overflow)?;
+        (net_before_vat, vat_amount)
+    };
     let total_with_vat = sale;
 
     // Calculate commission (based on gross profit)
-    let commission_amount = gross_profit * (commission_rate / Decimal::from(100));
+    let commission_amount = gross_profit
+        .checked_mul(checked_div(commission_rate, Decimal::from(100))?)
+        .ok_or(CalcError::Overflow)?;
 
     // Calculate net profit
-    let net_profit = gross_profit - commission_amount;
+    let net_profit = gross_profit.checked_sub(commission_amount).ok_or(CalcError::Overflow)?;
 
     // Calculate profit margin percentage
     let profit_margin = if sale > Decimal::ZERO {
-        (net_profit / sale) * Decimal::from(100)
+        checked_div(net_profit, sale)?
+            .checked_mul(Decimal::from(100))
+            .ok_or(CalcError::Overflow)?
     } else {
         Decimal::ZERO
     };
 
-    let result = BookingFinancials {
-        gross_profit: gross_profit.to_f64().unwrap_or(0.0),
-        vat_amount: vat_amount.to_f64().unwrap_or(0.0),
-        net_before_vat: net_before_vat.to_f64().unwrap_or(0.0),
-        total_with_vat: total_with_vat.to_f64().unwrap_or(0.0),
-        commission_amount: commission_amount.to_f64().unwrap_or(0.0),
-        net_profit: net_profit.to_f64().unwrap_or(0.0),
-        profit_margin_percentage: profit_margin.to_f64().unwrap_or(0.0),
+    Ok(BookingFinancials {
+        gross_profit: to_f64_checked(gross_profit)?,
+        vat_amount: to_f64_checked(vat_amount)?,
+        net_before_vat: to_f64_checked(net_before_vat)?,
+        total_with_vat: to_f64_checked(total_with_vat)?,
+        commission_amount: to_f64_checked(commission_amount)?,
+        net_profit: to_f64_checked(net_profit)?,
+        profit_margin_percentage: to_f64_checked(profit_margin)?,
+        fx_gain_loss: to_f64_checked(fx_gain_loss)?,
+    })
+}
+
+#[wasm_bindgen]
+pub fn calculate_booking_financials(input_json: &str) -> String {
+    let input: BookingInput = match serde_json::from_str(input_json) {
+        Ok(val) => val,
+        Err(e) => return format!("{{\"error\": \"Invalid input: {}\"}}", e),
     };
 
-    serde_json::to_string(&result).unwrap_or_else(|_| "{}".to_string())
+    match calculate_booking_financials_checked(&input) {
+        Ok(result) => serde_json::to_string(&result).unwrap_or_else(|_| "{}".to_string()),
+        Err(err) => calc_error_response(err),
+    }
 }
 
-// ==================== Journal Entry Generation ====================
+// ==================== Invoice Numbering ====================
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InvoiceNumberInput {
+    pub last_number: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InvoiceNumberResult {
+    pub next_number: String,
+}
+
+/// Increments the trailing digit run of an invoice number, preserving its zero-padding
+/// width (and growing the width only when the increment needs an extra digit), while
+/// keeping any prefix/suffix text untouched: `"INV-2024-0042"` -> `"INV-2024-0043"`,
+/// `"INV-099"` -> `"INV-100"`.
+fn compute_next_invoice_number(last: &str) -> Result<String, CalcError> {
+    let chars: Vec<char> = last.chars().collect();
+
+    let mut end = chars.len();
+    while end > 0 && !chars[end - 1].is_ascii_digit() {
+        end -= 1;
+    }
+    if end == 0 {
+        return Err(CalcError::InvalidInvoiceNumber);
+    }
+
+    let mut start = end;
+    while start > 0 && chars[start - 1].is_ascii_digit() {
+        start -= 1;
+    }
+
+    let prefix: String = chars[..start].iter().collect();
+    let digits: String = chars[start..end].iter().collect();
+    let suffix: String = chars[end..].iter().collect();
+
+    let width = digits.len();
+    let number: u64 = digits.parse().map_err(|_| CalcError::InvalidInvoiceNumber)?;
+    let next_number = number.checked_add(1).ok_or(CalcError::Overflow)?;
+
+    Ok(format!("{}{:0width$}{}", prefix, next_number, suffix, width = width))
+}
 
 #[wasm_bindgen]
-pub fn generate_journal_entries_for_booking(input_json: &str) -> String {
-    let input: BookingInput = match serde_json::from_str(input_json) {
+pub fn generate_next_invoice_number(last_number_json: &str) -> String {
+    let input: InvoiceNumberInput = match serde_json::from_str(last_number_json) {
         Ok(val) => val,
         Err(e) => return format!("{{\"error\": \"Invalid input: {}\"}}", e),
     };
 
-    let cost = Decimal::from_f64(input.cost_amount).unwrap_or(Decimal::ZERO);
-    let sale = Decimal::from_f64(input.sale_amount).unwrap_or(Decimal::ZERO);
-    let vat_rate = Decimal::from_f64(input.vat_rate).unwrap_or(Decimal::ZERO);
+    match compute_next_invoice_number(&input.last_number) {
+        Ok(next_number) => {
+            let result = InvoiceNumberResult { next_number };
+            serde_json::to_string(&result).unwrap_or_else(|_| "{}".to_string())
+        }
+        Err(err) => calc_error_response(err),
+    }
+}
+
+// ==================== Journal Entry Generation ====================
 
-    // Calculate VAT
-    let vat_divisor = Decimal::ONE + (vat_rate / Decimal::from(100));
-    let net_before_vat = sale / vat_divisor;
-    let vat_amount = sale - net_before_vat;
+fn generate_journal_entries_checked(input: &BookingInput) -> Result<JournalEntries, CalcError> {
+    validate_booking_input(input)?;
+
+    let cost = Decimal::from_f64(input.cost_amount).ok_or(CalcError::InvalidAmount)?;
+    let sale = Decimal::from_f64(input.sale_amount).ok_or(CalcError::InvalidAmount)?;
+    let vat_rate = Decimal::from_f64(input.vat_rate).ok_or(CalcError::InvalidAmount)?;
+
+    // Calculate VAT, unless the booking is VAT-exempt
+    let (net_before_vat, vat_amount) = if input.vat_exempt {
+        (sale, Decimal::ZERO)
+    } else {
+        let vat_divisor = Decimal::ONE
+            .checked_add(checked_div(vat_rate, Decimal::from(100))?)
+            .ok_or(CalcError::Overflow)?;
+        let net_before_vat = checked_div(sale, vat_divisor)?;
+        let vat_amount = sale.checked_sub(net_before_vat).ok_or(CalcError::Overflow)?;
+        (net_before_vat, vat_amount)
+    };
+
+    // All entries share one invoice reference when a prior invoice number is supplied.
+    let invoice_suffix = match &input.last_invoice_number {
+        Some(last) => format!(" [Invoice {}]", compute_next_invoice_number(last)?),
+        None => String::new(),
+    };
 
     let mut entries = Vec::new();
 
@@ -116,9 +353,9 @@ pub fn generate_journal_entries_for_booking(input_json: &str) -> String {
     entries.push(JournalEntry {
         account_code: "1201".to_string(),
         account_name: "Accounts Receivable - Customers".to_string(),
-        debit: sale.to_f64().unwrap_or(0.0),
+        debit: to_f64_checked(sale)?,
         credit: 0.0,
-        description: "Customer invoice for booking".to_string(),
+        description: format!("Customer invoice for booking{}", invoice_suffix),
     });
 
     // Entry 2: Credit Revenue Account
@@ -126,26 +363,28 @@ pub fn generate_journal_entries_for_booking(input_json: &str) -> String {
         account_code: "4101".to_string(),
         account_name: "Sales Revenue - Tourism Services".to_string(),
         debit: 0.0,
-        credit: net_before_vat.to_f64().unwrap_or(0.0),
-        description: "Revenue from booking (net of VAT)".to_string(),
+        credit: to_f64_checked(net_before_vat)?,
+        description: format!("Revenue from booking (net of VAT){}", invoice_suffix),
     });
 
-    // Entry 3: Credit VAT Payable
-    entries.push(JournalEntry {
-        account_code: "2301".to_string(),
-        account_name: "VAT Payable".to_string(),
-        debit: 0.0,
-        credit: vat_amount.to_f64().unwrap_or(0.0),
-        description: "VAT collected on sale".to_string(),
-    });
+    // Entry 3: Credit VAT Payable (omitted entirely for VAT-exempt bookings)
+    if !input.vat_exempt {
+        entries.push(JournalEntry {
+            account_code: "2301".to_string(),
+            account_name: "VAT Payable".to_string(),
+            debit: 0.0,
+            credit: to_f64_checked(vat_amount)?,
+            description: format!("VAT collected on sale{}", invoice_suffix),
+        });
+    }
 
     // Entry 4: Debit Cost of Sales
     entries.push(JournalEntry {
         account_code: "5101".to_string(),
         account_name: "Cost of Sales - Tourism Services".to_string(),
-        debit: cost.to_f64().unwrap_or(0.0),
+        debit: to_f64_checked(cost)?,
         credit: 0.0,
-        description: "Cost paid to supplier".to_string(),
+        description: format!("Cost paid to supplier{}", invoice_suffix),
     });
 
     // Entry 5: Credit Accounts Payable (Supplier)
@@ -153,8 +392,8 @@ pub fn generate_journal_entries_for_booking(input_json: &str) -> String {
         account_code: "2101".to_string(),
         account_name: "Accounts Payable - Suppliers".to_string(),
         debit: 0.0,
-        credit: cost.to_f64().unwrap_or(0.0),
-        description: "Amount due to supplier".to_string(),
+        credit: to_f64_checked(cost)?,
+        description: format!("Amount due to supplier{}", invoice_suffix),
     });
 
     // Calculate totals
@@ -162,14 +401,25 @@ pub fn generate_journal_entries_for_booking(input_json: &str) -> String {
     let total_credit: f64 = entries.iter().map(|e| e.credit).sum();
     let is_balanced = (total_debit - total_credit).abs() < 0.01; // Allow small rounding difference
 
-    let result = JournalEntries {
+    Ok(JournalEntries {
         entries,
         total_debit,
         total_credit,
         is_balanced,
+    })
+}
+
+#[wasm_bindgen]
+pub fn generate_journal_entries_for_booking(input_json: &str) -> String {
+    let input: BookingInput = match serde_json::from_str(input_json) {
+        Ok(val) => val,
+        Err(e) => return format!("{{\"error\": \"Invalid input: {}\"}}", e),
     };
 
-    serde_json::to_string(&result).unwrap_or_else(|_| "{}".to_string())
+    match generate_journal_entries_checked(&input) {
+        Ok(result) => serde_json::to_string(&result).unwrap_or_else(|_| "{}".to_string()),
+        Err(err) => calc_error_response(err),
+    }
 }
 
 // ==================== Batch Calculations ====================
@@ -177,12 +427,26 @@ pub fn generate_journal_entries_for_booking(input_json: &str) -> String {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct BatchBookingInput {
     pub bookings: Vec<BookingInput>,
+    /// Account code -> balance carried into the period from before the batch.
+    #[serde(default)]
+    pub opening_balances: Option<HashMap<String, f64>>,
+    /// Inclusive ISO reporting period. Bookings with a `booking_date` outside this range
+    /// are excluded from `generate_trial_balance`'s postings.
+    #[serde(default)]
+    pub start_date: Option<String>,
+    #[serde(default)]
+    pub end_date: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct BatchBookingResult {
     pub results: Vec<BookingFinancials>,
     pub summary: BatchSummary,
+    /// One entry per booking that failed validation or checked arithmetic, e.g.
+    /// `"booking #2: currency_mismatch - missing price-oracle rate for currency 'EUR'"`.
+    /// These bookings are excluded from `results` and the summary totals instead of being
+    /// silently dropped.
+    pub errors: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -194,71 +458,306 @@ pub struct BatchSummary {
     pub total_commission: f64,
     pub average_profit_margin: f64,
     pub booking_count: usize,
+    pub total_fx_gain: f64,
 }
 
-#[wasm_bindgen]
-pub fn calculate_batch_bookings(input_json: &str) -> String {
-    let input: BatchBookingInput = match serde_json::from_str(input_json) {
-        Ok(val) => val,
-        Err(e) => return format!("{{\"error\": \"Invalid input: {}\"}}", e),
-    };
+type BatchTotals = (Decimal, Decimal, Decimal, Decimal, Decimal, Decimal);
+
+/// Adds one booking's contribution onto the running batch totals. Returns `Err` instead of
+/// mutating anything when a step overflows, so the caller can route that single booking into
+/// `errors` and leave the totals computed so far untouched.
+fn accumulate_batch_totals(
+    totals: BatchTotals,
+    booking: &BookingInput,
+    result: &BookingFinancials,
+) -> Result<BatchTotals, CalcError> {
+    let (total_cost, total_revenue, total_profit, total_vat, total_commission, total_fx_gain) = totals;
+
+    let cost = Decimal::from_f64(booking.cost_amount).ok_or(CalcError::Overflow)?;
+    let revenue = Decimal::from_f64(booking.sale_amount).ok_or(CalcError::Overflow)?;
+    let net_profit = Decimal::from_f64(result.net_profit).ok_or(CalcError::Overflow)?;
+    let vat_amount = Decimal::from_f64(result.vat_amount).ok_or(CalcError::Overflow)?;
+    let commission_amount = Decimal::from_f64(result.commission_amount).ok_or(CalcError::Overflow)?;
+    let fx_gain_loss = Decimal::from_f64(result.fx_gain_loss).ok_or(CalcError::Overflow)?;
+
+    Ok((
+        total_cost.checked_add(cost).ok_or(CalcError::Overflow)?,
+        total_revenue.checked_add(revenue).ok_or(CalcError::Overflow)?,
+        total_profit.checked_add(net_profit).ok_or(CalcError::Overflow)?,
+        total_vat.checked_add(vat_amount).ok_or(CalcError::Overflow)?,
+        total_commission.checked_add(commission_amount).ok_or(CalcError::Overflow)?,
+        total_fx_gain.checked_add(fx_gain_loss).ok_or(CalcError::Overflow)?,
+    ))
+}
 
+fn calculate_batch_bookings_checked(input: &BatchBookingInput) -> Result<BatchBookingResult, CalcError> {
     let mut results = Vec::new();
+    let mut errors = Vec::new();
     let mut total_cost = Decimal::ZERO;
     let mut total_revenue = Decimal::ZERO;
     let mut total_profit = Decimal::ZERO;
     let mut total_vat = Decimal::ZERO;
     let mut total_commission = Decimal::ZERO;
-
-    for booking in &input.bookings {
-        let result_json = calculate_booking_financials(&serde_json::to_string(booking).unwrap());
-        if let Ok(result) = serde_json::from_str::<BookingFinancials>(&result_json) {
-            total_cost += Decimal::from_f64(booking.cost_amount).unwrap_or(Decimal::ZERO);
-            total_revenue += Decimal::from_f64(booking.sale_amount).unwrap_or(Decimal::ZERO);
-            total_profit += Decimal::from_f64(result.net_profit).unwrap_or(Decimal::ZERO);
-            total_vat += Decimal::from_f64(result.vat_amount).unwrap_or(Decimal::ZERO);
-            total_commission += Decimal::from_f64(result.commission_amount).unwrap_or(Decimal::ZERO);
-            
-            results.push(result);
+    let mut total_fx_gain = Decimal::ZERO;
+
+    for (index, booking) in input.bookings.iter().enumerate() {
+        let totals = (total_cost, total_revenue, total_profit, total_vat, total_commission, total_fx_gain);
+        let outcome = calculate_booking_financials_checked(booking)
+            .and_then(|result| accumulate_batch_totals(totals, booking, &result).map(|totals| (result, totals)));
+
+        match outcome {
+            Ok((result, (c, r, p, v, comm, fx))) => {
+                total_cost = c;
+                total_revenue = r;
+                total_profit = p;
+                total_vat = v;
+                total_commission = comm;
+                total_fx_gain = fx;
+                results.push(result);
+            }
+            Err(err) => {
+                errors.push(format!("booking #{}: {} - {}", index, err.error_code(), err.message()));
+            }
         }
     }
 
     let average_profit_margin = if total_revenue > Decimal::ZERO {
-        (total_profit / total_revenue) * Decimal::from(100)
+        checked_div(total_profit, total_revenue)?
+            .checked_mul(Decimal::from(100))
+            .ok_or(CalcError::Overflow)?
     } else {
         Decimal::ZERO
     };
 
     let summary = BatchSummary {
-        total_cost: total_cost.to_f64().unwrap_or(0.0),
-        total_revenue: total_revenue.to_f64().unwrap_or(0.0),
-        total_profit: total_profit.to_f64().unwrap_or(0.0),
-        total_vat: total_vat.to_f64().unwrap_or(0.0),
-        total_commission: total_commission.to_f64().unwrap_or(0.0),
-        average_profit_margin: average_profit_margin.to_f64().unwrap_or(0.0),
-        booking_count: input.bookings.len(),
+        total_cost: to_f64_checked(total_cost)?,
+        total_revenue: to_f64_checked(total_revenue)?,
+        total_profit: to_f64_checked(total_profit)?,
+        total_vat: to_f64_checked(total_vat)?,
+        total_commission: to_f64_checked(total_commission)?,
+        average_profit_margin: to_f64_checked(average_profit_margin)?,
+        booking_count: results.len(),
+        total_fx_gain: to_f64_checked(total_fx_gain)?,
     };
 
-    let result = BatchBookingResult { results, summary };
+    Ok(BatchBookingResult { results, summary, errors })
+}
+
+#[wasm_bindgen]
+pub fn calculate_batch_bookings(input_json: &str) -> String {
+    let input: BatchBookingInput = match serde_json::from_str(input_json) {
+        Ok(val) => val,
+        Err(e) => return format!("{{\"error\": \"Invalid input: {}\"}}", e),
+    };
+
+    match calculate_batch_bookings_checked(&input) {
+        Ok(result) => serde_json::to_string(&result).unwrap_or_else(|_| "{}".to_string()),
+        Err(err) => calc_error_response(err),
+    }
+}
+
+// ==================== Trial Balance ====================
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TrialBalanceAccount {
+    pub account_code: String,
+    pub account_name: String,
+    pub opening_balance: f64,
+    pub total_debits: f64,
+    pub total_credits: f64,
+    pub closing_balance: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TrialBalance {
+    pub accounts: Vec<TrialBalanceAccount>,
+    pub is_balanced: bool,
+    /// Bookings excluded from posting: outside `start_date`/`end_date`, or failing
+    /// validation/checked arithmetic.
+    pub skipped_count: usize,
+}
+
+fn booking_in_period(booking: &BookingInput, start_date: &Option<String>, end_date: &Option<String>) -> bool {
+    let Some(booking_date) = &booking.booking_date else {
+        return true;
+    };
+    if let Some(start) = start_date {
+        if booking_date.as_str() < start.as_str() {
+            return false;
+        }
+    }
+    if let Some(end) = end_date {
+        if booking_date.as_str() > end.as_str() {
+            return false;
+        }
+    }
+    true
+}
+
+/// Posts every in-period booking's journal entries onto the opening balances and reports a
+/// running per-account trial balance for the batch.
+#[wasm_bindgen]
+pub fn generate_trial_balance(input_json: &str) -> String {
+    let input: BatchBookingInput = match serde_json::from_str(input_json) {
+        Ok(val) => val,
+        Err(e) => return format!("{{\"error\": \"Invalid input: {}\"}}", e),
+    };
+
+    let opening_balances = input.opening_balances.clone().unwrap_or_default();
+    let mut ledger: HashMap<String, TrialBalanceAccount> = HashMap::new();
+    let mut skipped_count = 0usize;
+
+    for (account_code, opening_balance) in &opening_balances {
+        ledger.entry(account_code.clone()).or_insert_with(|| TrialBalanceAccount {
+            account_code: account_code.clone(),
+            account_name: account_code.clone(),
+            opening_balance: *opening_balance,
+            total_debits: 0.0,
+            total_credits: 0.0,
+            closing_balance: 0.0,
+        });
+    }
+
+    for booking in &input.bookings {
+        if !booking_in_period(booking, &input.start_date, &input.end_date) {
+            skipped_count += 1;
+            continue;
+        }
+
+        let entries = match generate_journal_entries_checked(booking) {
+            Ok(result) => result.entries,
+            Err(_) => {
+                skipped_count += 1;
+                continue;
+            }
+        };
+
+        for entry in entries {
+            let account = ledger.entry(entry.account_code.clone()).or_insert_with(|| TrialBalanceAccount {
+                account_code: entry.account_code.clone(),
+                account_name: entry.account_name.clone(),
+                opening_balance: *opening_balances.get(&entry.account_code).unwrap_or(&0.0),
+                total_debits: 0.0,
+                total_credits: 0.0,
+                closing_balance: 0.0,
+            });
+            account.total_debits += entry.debit;
+            account.total_credits += entry.credit;
+        }
+    }
+
+    let mut accounts: Vec<TrialBalanceAccount> = ledger.into_values().collect();
+    for account in &mut accounts {
+        account.closing_balance = account.opening_balance + account.total_debits - account.total_credits;
+    }
+    accounts.sort_by(|a, b| a.account_code.cmp(&b.account_code));
+
+    let closing_sum: f64 = accounts.iter().map(|a| a.closing_balance).sum();
+    let is_balanced = closing_sum.abs() < 0.01;
+
+    let result = TrialBalance { accounts, is_balanced, skipped_count };
 
     serde_json::to_string(&result).unwrap_or_else(|_| "{}".to_string())
 }
 
+// ==================== VAT Summary Report ====================
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VatSummaryGroup {
+    pub cost_centre: String,
+    pub vat_rate: f64,
+    pub net_before_vat: f64,
+    pub vat_amount: f64,
+    pub net_vat_exempted: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VatSummaryReport {
+    pub groups: Vec<VatSummaryGroup>,
+    /// One entry per booking that failed validation or checked arithmetic, e.g.
+    /// `"booking #2: currency_mismatch - missing price-oracle rate for currency 'EUR'"`.
+    /// These bookings are excluded from `groups` instead of being silently dropped.
+    pub errors: Vec<String>,
+}
+
+/// Groups a batch of bookings by `(cost_centre, vat_rate)` for tax reporting, keeping
+/// VAT-exempt net totals separate from standard-rated net/VAT totals.
+#[wasm_bindgen]
+pub fn generate_vat_summary(input_json: &str) -> String {
+    let input: BatchBookingInput = match serde_json::from_str(input_json) {
+        Ok(val) => val,
+        Err(e) => return format!("{{\"error\": \"Invalid input: {}\"}}", e),
+    };
+
+    let mut groups: Vec<VatSummaryGroup> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
+
+    for (index, booking) in input.bookings.iter().enumerate() {
+        let result = match calculate_booking_financials_checked(booking) {
+            Ok(val) => val,
+            Err(err) => {
+                errors.push(format!("booking #{}: {} - {}", index, err.error_code(), err.message()));
+                continue;
+            }
+        };
+
+        let cost_centre = booking.cost_centre.clone().unwrap_or_default();
+        let group = match groups
+            .iter_mut()
+            .find(|g| g.cost_centre == cost_centre && g.vat_rate == booking.vat_rate)
+        {
+            Some(g) => g,
+            None => {
+                groups.push(VatSummaryGroup {
+                    cost_centre,
+                    vat_rate: booking.vat_rate,
+                    net_before_vat: 0.0,
+                    vat_amount: 0.0,
+                    net_vat_exempted: 0.0,
+                });
+                groups.last_mut().unwrap()
+            }
+        };
+
+        if booking.vat_exempt {
+            group.net_vat_exempted += result.net_before_vat;
+        } else {
+            group.net_before_vat += result.net_before_vat;
+            group.vat_amount += result.vat_amount;
+        }
+    }
+
+    let report = VatSummaryReport { groups, errors };
+
+    serde_json::to_string(&report).unwrap_or_else(|_| "{}".to_string())
+}
+
 // ==================== Unit Tests ====================
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_calculate_booking_financials() {
-        let input = BookingInput {
+    fn default_booking() -> BookingInput {
+        BookingInput {
             cost_amount: 1000.0,
             sale_amount: 1500.0,
             vat_rate: 5.0,
             commission_rate: 10.0,
             currency: "USD".to_string(),
-        };
+            cost_currency: None,
+            price_oracle: None,
+            base_currency: None,
+            vat_exempt: false,
+            cost_centre: None,
+            last_invoice_number: None,
+            booking_date: None,
+        }
+    }
+
+    #[test]
+    fn test_calculate_booking_financials() {
+        let input = default_booking();
 
         let input_json = serde_json::to_string(&input).unwrap();
         let result_json = calculate_booking_financials(&input_json);
@@ -272,13 +771,7 @@ mod tests {
 
     #[test]
     fn test_journal_entries_balanced() {
-        let input = BookingInput {
-            cost_amount: 1000.0,
-            sale_amount: 1500.0,
-            vat_rate: 5.0,
-            commission_rate: 10.0,
-            currency: "USD".to_string(),
-        };
+        let input = default_booking();
 
         let input_json = serde_json::to_string(&input).unwrap();
         let result_json = generate_journal_entries_for_booking(&input_json);
@@ -287,4 +780,231 @@ mod tests {
         assert!(result.is_balanced);
         assert_eq!(result.total_debit, result.total_credit);
     }
+
+    #[test]
+    fn test_next_invoice_number_increments_preserving_width() {
+        assert_eq!(compute_next_invoice_number("INV-2024-0042").unwrap(), "INV-2024-0043");
+        assert_eq!(compute_next_invoice_number("INV-099").unwrap(), "INV-100");
+    }
+
+    #[test]
+    fn test_trial_balance_skips_bookings_outside_period() {
+        let mut in_period = default_booking();
+        in_period.booking_date = Some("2024-03-15".to_string());
+        let mut out_of_period = in_period.clone();
+        out_of_period.booking_date = Some("2024-01-01".to_string());
+
+        let input = BatchBookingInput {
+            bookings: vec![in_period, out_of_period],
+            opening_balances: None,
+            start_date: Some("2024-03-01".to_string()),
+            end_date: Some("2024-03-31".to_string()),
+        };
+
+        let input_json = serde_json::to_string(&input).unwrap();
+        let result_json = generate_trial_balance(&input_json);
+        let result: TrialBalance = serde_json::from_str(&result_json).unwrap();
+
+        assert_eq!(result.skipped_count, 1);
+        assert!(result.is_balanced);
+    }
+
+    #[test]
+    fn test_trial_balance_seeds_opening_balance_only_accounts() {
+        let booking = default_booking();
+
+        let mut opening_balances = HashMap::new();
+        opening_balances.insert("9999".to_string(), 500.0);
+
+        let input = BatchBookingInput {
+            bookings: vec![booking],
+            opening_balances: Some(opening_balances),
+            start_date: None,
+            end_date: None,
+        };
+
+        let input_json = serde_json::to_string(&input).unwrap();
+        let result_json = generate_trial_balance(&input_json);
+        let result: TrialBalance = serde_json::from_str(&result_json).unwrap();
+
+        let seeded_account = result.accounts.iter().find(|a| a.account_code == "9999");
+        assert!(seeded_account.is_some());
+        assert_eq!(seeded_account.unwrap().closing_balance, 500.0);
+        assert!(!result.is_balanced);
+    }
+
+    #[test]
+    fn test_fx_conversion_converts_cost_and_reports_gain() {
+        let mut booking = default_booking();
+        booking.cost_amount = 1000.0;
+        booking.sale_amount = 1000.0;
+        booking.currency = "EUR".to_string();
+        booking.cost_currency = Some("USD".to_string());
+        booking.base_currency = Some("EUR".to_string());
+        let mut oracle = HashMap::new();
+        oracle.insert("USD".to_string(), 0.9);
+        booking.price_oracle = Some(oracle);
+
+        let input_json = serde_json::to_string(&booking).unwrap();
+        let result_json = calculate_booking_financials(&input_json);
+        let result: BookingFinancials = serde_json::from_str(&result_json).unwrap();
+
+        assert!((result.gross_profit - 100.0).abs() < 0.001);
+        assert!((result.fx_gain_loss - 100.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_missing_oracle_rate_returns_currency_mismatch() {
+        let mut booking = default_booking();
+        booking.currency = "EUR".to_string();
+        booking.cost_currency = Some("USD".to_string());
+
+        let input_json = serde_json::to_string(&booking).unwrap();
+        let result_json = calculate_booking_financials(&input_json);
+        let response: CalcErrorResponse = serde_json::from_str(&result_json).unwrap();
+
+        assert_eq!(response.error_code, "currency_mismatch");
+    }
+
+    #[test]
+    fn test_negative_oracle_rate_is_rejected() {
+        let mut booking = default_booking();
+        booking.currency = "EUR".to_string();
+        booking.cost_currency = Some("USD".to_string());
+        let mut oracle = HashMap::new();
+        oracle.insert("USD".to_string(), -1.0);
+        booking.price_oracle = Some(oracle);
+
+        let input_json = serde_json::to_string(&booking).unwrap();
+        let result_json = calculate_booking_financials(&input_json);
+        let response: CalcErrorResponse = serde_json::from_str(&result_json).unwrap();
+
+        assert_eq!(response.error_code, "negative_rate");
+    }
+
+    #[test]
+    fn test_invalid_booking_returns_calc_error_not_success() {
+        let mut negative_cost = default_booking();
+        negative_cost.cost_amount = -1.0;
+        let response_json = calculate_booking_financials(&serde_json::to_string(&negative_cost).unwrap());
+        let response: CalcErrorResponse = serde_json::from_str(&response_json).unwrap();
+        assert_eq!(response.error_code, "invalid_amount");
+
+        let mut high_vat = default_booking();
+        high_vat.vat_rate = 100.0;
+        let response_json = calculate_booking_financials(&serde_json::to_string(&high_vat).unwrap());
+        let response: CalcErrorResponse = serde_json::from_str(&response_json).unwrap();
+        assert_eq!(response.error_code, "invalid_amount");
+    }
+
+    #[test]
+    fn test_batch_routes_bad_booking_into_errors_without_corrupting_summary() {
+        let good = default_booking();
+        let mut bad = default_booking();
+        bad.cost_amount = -1.0;
+
+        let input = BatchBookingInput {
+            bookings: vec![good, bad],
+            opening_balances: None,
+            start_date: None,
+            end_date: None,
+        };
+
+        let result_json = calculate_batch_bookings(&serde_json::to_string(&input).unwrap());
+        let result: BatchBookingResult = serde_json::from_str(&result_json).unwrap();
+
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.summary.booking_count, 1);
+        assert_eq!(result.results.len(), 1);
+    }
+
+    #[test]
+    fn test_batch_accumulation_overflow_routes_to_errors_without_wiping_the_batch() {
+        let mut huge = default_booking();
+        huge.cost_amount = 5e28;
+        huge.sale_amount = 5e28;
+        huge.vat_rate = 0.0;
+        huge.commission_rate = 0.0;
+
+        let input = BatchBookingInput {
+            bookings: vec![huge.clone(), huge],
+            opening_balances: None,
+            start_date: None,
+            end_date: None,
+        };
+
+        let result_json = calculate_batch_bookings(&serde_json::to_string(&input).unwrap());
+        let result: BatchBookingResult = serde_json::from_str(&result_json).unwrap();
+
+        assert_eq!(result.results.len(), 1);
+        assert_eq!(result.errors.len(), 1);
+        assert!(result.errors[0].contains("overflow"));
+    }
+
+    #[test]
+    fn test_invoice_number_without_digits_returns_dedicated_error() {
+        let err = compute_next_invoice_number("INV-ABC").unwrap_err();
+        assert_eq!(err.error_code(), "invalid_invoice_number");
+    }
+
+    #[test]
+    fn test_vat_exempt_booking_skips_vat_and_its_journal_entry() {
+        let mut booking = default_booking();
+        booking.vat_exempt = true;
+
+        let financials_json = calculate_booking_financials(&serde_json::to_string(&booking).unwrap());
+        let financials: BookingFinancials = serde_json::from_str(&financials_json).unwrap();
+        assert_eq!(financials.vat_amount, 0.0);
+
+        let journal_json = generate_journal_entries_for_booking(&serde_json::to_string(&booking).unwrap());
+        let journal: JournalEntries = serde_json::from_str(&journal_json).unwrap();
+        assert!(!journal.entries.iter().any(|e| e.account_code == "2301"));
+    }
+
+    #[test]
+    fn test_vat_summary_groups_by_cost_centre_and_separates_exempt_net() {
+        let mut standard = default_booking();
+        standard.cost_centre = Some("TOURS".to_string());
+
+        let mut exempt = default_booking();
+        exempt.cost_centre = Some("TOURS".to_string());
+        exempt.vat_exempt = true;
+
+        let input = BatchBookingInput {
+            bookings: vec![standard, exempt],
+            opening_balances: None,
+            start_date: None,
+            end_date: None,
+        };
+
+        let result_json = generate_vat_summary(&serde_json::to_string(&input).unwrap());
+        let result: VatSummaryReport = serde_json::from_str(&result_json).unwrap();
+
+        assert_eq!(result.groups.len(), 1);
+        let group = &result.groups[0];
+        assert_eq!(group.cost_centre, "TOURS");
+        assert!(group.net_before_vat > 0.0);
+        assert!(group.net_vat_exempted > 0.0);
+        assert!(result.errors.is_empty());
+    }
+
+    #[test]
+    fn test_vat_summary_surfaces_bad_booking_in_errors_instead_of_dropping_it() {
+        let good = default_booking();
+        let mut bad = default_booking();
+        bad.cost_amount = -1.0;
+
+        let input = BatchBookingInput {
+            bookings: vec![good, bad],
+            opening_balances: None,
+            start_date: None,
+            end_date: None,
+        };
+
+        let result_json = generate_vat_summary(&serde_json::to_string(&input).unwrap());
+        let result: VatSummaryReport = serde_json::from_str(&result_json).unwrap();
+
+        assert_eq!(result.errors.len(), 1);
+        assert!(result.errors[0].contains("invalid_amount"));
+    }
 }